@@ -2,18 +2,58 @@
 //!
 //! It is based atop murmurhash3 and uses file size and sample data to construct the hash.
 
-use std::fs::File;
+use std::collections::HashMap;
+use std::fs::{self, File};
 use std::io::{BufReader, Cursor, Read, Result, Seek, SeekFrom};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
 
 const SAMPLE_THRESHOLD: u32 = 128 * 1024;
 const SAMPLE_SIZE: u32 = 16 * 1024;
 
+/// Selects the inner hash backend used to mix imohash's sampled bytes.
+///
+/// imohash's sampling scheme (size + first/middle/last chunks) is independent
+/// of the function used to mix the collected bytes down to 128 bits.
+/// `Murmur3X64_128` reproduces the original imohash output, while `Blake3` and
+/// `Xxh3` trade a little speed for stronger collision resistance. The final
+/// varint size-embedding step is identical across all backends.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    /// 128-bit MurmurHash3 (x64 variant). This is the default and matches the
+    /// original Go imohash format.
+    Murmur3X64_128,
+    /// BLAKE3, truncated to its first 128 bits.
+    Blake3,
+    /// 128-bit XXH3.
+    Xxh3,
+}
+
+impl HashAlgorithm {
+    /// Mixes the sampled bytes down to a 128-bit value.
+    fn mix(&self, sampled: &[u8]) -> u128 {
+        match self {
+            HashAlgorithm::Murmur3X64_128 => {
+                // Reading from an in-memory cursor is infallible.
+                let hash_result = murmur3::murmur3_x64_128(&mut Cursor::new(sampled), 0).unwrap();
+                hash_result.rotate_right(64).swap_bytes()
+            }
+            HashAlgorithm::Blake3 => {
+                let digest = blake3::hash(sampled);
+                u128::from_le_bytes(digest.as_bytes()[..16].try_into().unwrap())
+            }
+            HashAlgorithm::Xxh3 => xxhash_rust::xxh3::xxh3_128(sampled),
+        }
+    }
+}
+
 /// A hasher which holds the custom sample parameters, and provides the APIs
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Hasher {
     sample_threshold: u32,
     sample_size: u32,
+    algorithm: HashAlgorithm,
 }
 
 impl Hasher {
@@ -22,6 +62,7 @@ impl Hasher {
         Self {
             sample_threshold: SAMPLE_THRESHOLD,
             sample_size: SAMPLE_SIZE,
+            algorithm: HashAlgorithm::Murmur3X64_128,
         }
     }
 
@@ -32,6 +73,18 @@ impl Hasher {
         Self {
             sample_threshold: threshold,
             sample_size: size,
+            algorithm: HashAlgorithm::Murmur3X64_128,
+        }
+    }
+
+    /// Creates a new Hasher using the default sample parameters but the given
+    /// inner hash backend. The sampling scheme is unchanged, so only the mixing
+    /// function differs from [`Hasher::new`].
+    pub fn with_algorithm(algorithm: HashAlgorithm) -> Self {
+        Self {
+            sample_threshold: SAMPLE_THRESHOLD,
+            sample_size: SAMPLE_SIZE,
+            algorithm,
         }
     }
 
@@ -51,6 +104,85 @@ impl Hasher {
         self.hash(&mut reader)
     }
 
+    /// Recursively finds duplicate files under `root`.
+    ///
+    /// Candidates are first grouped by byte length — a free pre-filter, since
+    /// imohash folds the size into the hash, so files of different sizes can
+    /// never collide. The surviving size-collision groups are then hashed in
+    /// parallel and regrouped by hash value. Only groups of two or more files
+    /// sharing a hash are returned.
+    ///
+    /// Symlinks are silently skipped: the walk uses `file_type()`, which does
+    /// not follow links, so a symlink is treated as neither a directory to
+    /// descend nor a regular file to hash.
+    pub fn find_duplicates(&self, root: &Path) -> Result<HashMap<u128, Vec<PathBuf>>> {
+        // Collect every regular file under `root`, tagged with its length.
+        let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+        let mut stack = vec![root.to_path_buf()];
+        while let Some(dir) = stack.pop() {
+            for entry in fs::read_dir(&dir)? {
+                let entry = entry?;
+                let file_type = entry.file_type()?;
+                if file_type.is_dir() {
+                    stack.push(entry.path());
+                } else if file_type.is_file() {
+                    let len = entry.metadata()?.len();
+                    by_size.entry(len).or_default().push(entry.path());
+                }
+            }
+        }
+
+        // Only files sharing a size can share a hash, so drop the singletons
+        // before paying for any hashing.
+        let candidates: Vec<PathBuf> = by_size
+            .into_values()
+            .filter(|paths| paths.len() > 1)
+            .flatten()
+            .collect();
+
+        // Hash the candidates in parallel, then regroup by hash value.
+        let hashed: Vec<(u128, PathBuf)> = candidates
+            .into_par_iter()
+            .map(|path| self.sum_path(&path).map(|hash| (hash, path)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut by_hash: HashMap<u128, Vec<PathBuf>> = HashMap::new();
+        for (hash, path) in hashed {
+            by_hash.entry(hash).or_default().push(path);
+        }
+        by_hash.retain(|_, paths| paths.len() > 1);
+        Ok(by_hash)
+    }
+
+    fn sum_path(&self, path: &Path) -> Result<u128> {
+        let f = File::open(path)?;
+        let mut reader = BufReader::new(f);
+        self.hash(&mut reader)
+    }
+
+    /// Hashes a file, consulting `cache` first.
+    ///
+    /// On a cache hit for the file's current `(path, mtime, len)` the stored
+    /// hash is returned without touching the file contents. On a miss — or when
+    /// the file's mtime or length has changed — the file is hashed just as
+    /// [`Hasher::sum_file`] would hash it and the result is recorded back into
+    /// the cache.
+    pub fn sum_file_cached(&self, path: &str, cache: &mut HashCache) -> Result<u128> {
+        let input_path = Path::new(path.trim());
+        let canonical = input_path.canonicalize()?;
+        let metadata = fs::metadata(&canonical)?;
+        let len = metadata.len();
+        let mtime = mtime_nanos(&metadata)?;
+
+        if let Some(hash) = cache.get(&canonical, mtime, len) {
+            return Ok(hash);
+        }
+
+        let hash = self.sum_path(&canonical)?;
+        cache.insert(&canonical, mtime, len, hash);
+        Ok(hash)
+    }
+
     fn hash<R>(&self, reader: &mut R) -> Result<u128>
     where
         R: Read + Seek,
@@ -76,8 +208,7 @@ impl Hasher {
             buffer.append(&mut middle_buf);
             buffer.append(&mut last_buf);
         }
-        let hash_result = murmur3::murmur3_x64_128(&mut Cursor::new(buffer), 0)?;
-        let mut hash_bytes = hash_result.rotate_right(64).swap_bytes().to_le_bytes();
+        let mut hash_bytes = self.algorithm.mix(&buffer).to_le_bytes();
         put_uvarint(&mut hash_bytes, size);
         Ok(u128::from_le_bytes(hash_bytes))
     }
@@ -89,6 +220,283 @@ impl Default for Hasher {
     }
 }
 
+const CACHE_MAGIC: u64 = 0x494d_4f48_4153_4801;
+const CACHE_HEADER_SIZE: usize = 16;
+const CACHE_ENTRY_SIZE: usize = 40;
+const DEFAULT_CACHE_CAPACITY: u64 = 1 << 16;
+
+/// A persistent, memory-mapped cache of previously computed hashes.
+///
+/// Entries are keyed by `(canonical_path, mtime, len)` so an unchanged file is
+/// never rehashed across runs. The backing file is a fixed-capacity,
+/// open-addressing hash table of 40-byte entries that is mmap'd directly — no
+/// parse step on load — and flushed back to disk when the cache is dropped.
+///
+/// Because the capacity is fixed at creation, a full table silently stops
+/// accepting new entries; size it for the trees you scan.
+pub struct HashCache {
+    map: memmap2::MmapMut,
+    capacity: u64,
+}
+
+impl HashCache {
+    /// Opens a cache at `path`, creating it with the default capacity if
+    /// necessary. An existing cache keeps the capacity recorded in its header.
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::with_capacity(path, DEFAULT_CACHE_CAPACITY)
+    }
+
+    /// Opens a cache at `path`, creating it sized for `capacity` slots if
+    /// necessary. An existing cache keeps the capacity recorded in its header,
+    /// and `capacity` is then ignored.
+    pub fn with_capacity(path: &Path, capacity: u64) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        // Peek the magic of any existing header. A file too short to carry one
+        // is brand new; a zero magic means a prior creation crashed between
+        // set_len and the header write. Either way we (re)initialize it. A file
+        // carrying a different, nonzero magic is some other file — refuse it
+        // rather than clobber its contents.
+        let original_len = file.metadata()?.len();
+        let existing_magic = if original_len >= CACHE_HEADER_SIZE as u64 {
+            let map = unsafe { memmap2::Mmap::map(&file)? };
+            u64::from_le_bytes(map[0..8].try_into().unwrap())
+        } else {
+            0
+        };
+        if existing_magic != 0 && existing_magic != CACHE_MAGIC {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "not an imohash cache file",
+            ));
+        }
+
+        let initialize = existing_magic == 0;
+        if initialize {
+            let len = CACHE_HEADER_SIZE as u64 + capacity * CACHE_ENTRY_SIZE as u64;
+            file.set_len(len)?;
+        }
+
+        // SAFETY: the cache file is owned by this process for the lifetime of
+        // the mapping, like any other single-writer mmap'd table.
+        let mut map = unsafe { memmap2::MmapMut::map_mut(&file)? };
+        if initialize {
+            map.iter_mut().for_each(|b| *b = 0);
+            map[0..8].copy_from_slice(&CACHE_MAGIC.to_le_bytes());
+            map[8..16].copy_from_slice(&capacity.to_le_bytes());
+        }
+
+        // Trust the header only after sanity-checking it: a zero or oversized
+        // capacity would divide-by-zero or slice past the mapping on use.
+        let capacity = u64::from_le_bytes(map[8..16].try_into().unwrap());
+        let fits = capacity
+            .checked_mul(CACHE_ENTRY_SIZE as u64)
+            .and_then(|v| v.checked_add(CACHE_HEADER_SIZE as u64))
+            .is_some_and(|needed| needed <= map.len() as u64);
+        if capacity == 0 || !fits {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "corrupt imohash cache header",
+            ));
+        }
+
+        Ok(Self { map, capacity })
+    }
+
+    /// Looks up the hash recorded for `(path, mtime, len)`, if any.
+    pub fn get(&self, path: &Path, mtime: u64, len: u64) -> Option<u128> {
+        let key = path_hash(path);
+        let mut slot = key % self.capacity;
+        for _ in 0..self.capacity {
+            let entry = self.entry(slot);
+            let entry_key = u64::from_le_bytes(entry[0..8].try_into().unwrap());
+            if entry_key == 0 {
+                return None;
+            }
+            if entry_key == key
+                && u64::from_le_bytes(entry[8..16].try_into().unwrap()) == mtime
+                && u64::from_le_bytes(entry[16..24].try_into().unwrap()) == len
+            {
+                return Some(u128::from_le_bytes(entry[24..40].try_into().unwrap()));
+            }
+            slot = (slot + 1) % self.capacity;
+        }
+        None
+    }
+
+    /// Records `value` for `(path, mtime, len)`, replacing any prior entry for
+    /// the same path. Does nothing if the table is full.
+    pub fn insert(&mut self, path: &Path, mtime: u64, len: u64, value: u128) {
+        let key = path_hash(path);
+        let mut slot = key % self.capacity;
+        for _ in 0..self.capacity {
+            let entry_key = {
+                let entry = self.entry(slot);
+                u64::from_le_bytes(entry[0..8].try_into().unwrap())
+            };
+            if entry_key == 0 || entry_key == key {
+                let entry = self.entry_mut(slot);
+                entry[0..8].copy_from_slice(&key.to_le_bytes());
+                entry[8..16].copy_from_slice(&mtime.to_le_bytes());
+                entry[16..24].copy_from_slice(&len.to_le_bytes());
+                entry[24..40].copy_from_slice(&value.to_le_bytes());
+                return;
+            }
+            slot = (slot + 1) % self.capacity;
+        }
+    }
+
+    fn entry(&self, slot: u64) -> &[u8] {
+        let start = CACHE_HEADER_SIZE + slot as usize * CACHE_ENTRY_SIZE;
+        &self.map[start..start + CACHE_ENTRY_SIZE]
+    }
+
+    fn entry_mut(&mut self, slot: u64) -> &mut [u8] {
+        let start = CACHE_HEADER_SIZE + slot as usize * CACHE_ENTRY_SIZE;
+        &mut self.map[start..start + CACHE_ENTRY_SIZE]
+    }
+}
+
+impl Drop for HashCache {
+    fn drop(&mut self) {
+        let _ = self.map.flush();
+    }
+}
+
+/// A stable FNV-1a hash of a path, used as the cache's open-addressing key.
+/// Never returns 0, which the table reserves for empty slots.
+fn path_hash(path: &Path) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in path.as_os_str().as_encoded_bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash | 1
+}
+
+fn mtime_nanos(metadata: &std::fs::Metadata) -> Result<u64> {
+    let mtime = metadata.modified()?;
+    let dur = mtime
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+    Ok(dur.as_nanos() as u64)
+}
+
+impl Hasher {
+    /// Recovers the file size that `hash()` embedded into the low bytes of a
+    /// hash value.
+    ///
+    /// imohash folds the size into the hash as a uvarint, so it can be read back
+    /// out cheaply — useful for pre-filtering by size before comparing full
+    /// hashes, and for interoperability with the original Go imohash format.
+    pub fn size_from_hash(hash: u128) -> u64 {
+        read_uvarint(&hash.to_le_bytes())
+    }
+}
+
+/// An incremental, buffering wrapper that exposes imohash through the standard
+/// [`std::io::Write`] and [`digest::Digest`] surfaces.
+///
+/// imohash's middle sample depends on the total input length, which is unknown
+/// until the stream ends, so the correct streaming implementation buffers all
+/// written bytes and runs the size+sample logic at finalization. This makes the
+/// wrapper convenient for moderate inputs; for huge files prefer
+/// [`Hasher::sum_file`], which samples directly and buffers nothing.
+#[derive(Clone, Debug, Default)]
+pub struct ImoHasher {
+    hasher: Hasher,
+    buffer: Vec<u8>,
+}
+
+impl ImoHasher {
+    /// Creates a new incremental hasher using the default sample parameters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new incremental hasher driven by the given [`Hasher`].
+    pub fn with_hasher(hasher: Hasher) -> Self {
+        Self {
+            hasher,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Consumes the accumulated bytes and returns the 16-byte imohash output.
+    pub fn finalize(self) -> [u8; 16] {
+        // Hashing an in-memory buffer is infallible.
+        self.hasher.sum(&self.buffer).unwrap().to_le_bytes()
+    }
+}
+
+impl std::io::Write for ImoHasher {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl digest::HashMarker for ImoHasher {}
+
+impl digest::OutputSizeUser for ImoHasher {
+    type OutputSize = digest::consts::U16;
+}
+
+impl digest::Update for ImoHasher {
+    fn update(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+}
+
+impl digest::FixedOutput for ImoHasher {
+    fn finalize_into(self, out: &mut digest::Output<Self>) {
+        out.copy_from_slice(&self.finalize());
+    }
+}
+
+impl digest::FixedOutputReset for ImoHasher {
+    fn finalize_into_reset(&mut self, out: &mut digest::Output<Self>) {
+        out.copy_from_slice(&self.hasher.sum(&self.buffer).unwrap().to_le_bytes());
+        self.buffer.clear();
+    }
+}
+
+impl digest::Reset for ImoHasher {
+    fn reset(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+fn read_uvarint(buffer: impl AsRef<[u8]>) -> u64 {
+    let buf = buffer.as_ref();
+    let mut x: u64 = 0;
+    let mut shift = 0u32;
+    // A u64 uvarint occupies at most 10 bytes (Go's binary.MaxVarintLen64).
+    // Anything longer, or a non-terminating / overflowing run, is malformed;
+    // mirror binary.Uvarint and bail out with 0 rather than shifting past 64.
+    for (i, &b) in buf.iter().take(10).enumerate() {
+        if b < 0x80 {
+            // The 10th byte can only carry the single top bit of a u64.
+            if i == 9 && b > 1 {
+                return 0;
+            }
+            return x | ((b as u64) << shift);
+        }
+        x |= ((b & 0x7f) as u64) << shift;
+        shift += 7;
+    }
+    0
+}
+
 fn put_uvarint(mut buffer: impl AsMut<[u8]>, x: u64) -> usize {
     let mut i = 0;
     let mut mx = x;
@@ -118,6 +526,79 @@ mod tests {
         assert_eq!(buffer, expected);
     }
 
+    #[test]
+    fn test_algorithms() {
+        let data = m(500_000);
+
+        let murmur = Hasher::with_algorithm(HashAlgorithm::Murmur3X64_128);
+        let blake3 = Hasher::with_algorithm(HashAlgorithm::Blake3);
+        let xxh3 = Hasher::with_algorithm(HashAlgorithm::Xxh3);
+
+        // the default hasher uses murmur3
+        assert_eq!(murmur.sum(&data).unwrap(), Hasher::new().sum(&data).unwrap());
+
+        let hm = murmur.sum(&data).unwrap();
+        let hb = blake3.sum(&data).unwrap();
+        let hx = xxh3.sum(&data).unwrap();
+
+        // each backend mixes differently, so the outputs diverge
+        assert_ne!(hm, hb);
+        assert_ne!(hm, hx);
+        assert_ne!(hb, hx);
+
+        // but every backend folds the same size into the low bytes
+        assert_eq!(Hasher::size_from_hash(hm), 500_000);
+        assert_eq!(Hasher::size_from_hash(hb), 500_000);
+        assert_eq!(Hasher::size_from_hash(hx), 500_000);
+
+        // and each backend is deterministic: hashing the same data again
+        // gives the same value
+        assert_eq!(
+            hex::encode(hb.to_le_bytes()),
+            hex::encode(blake3.sum(&data).unwrap().to_le_bytes())
+        );
+        assert_eq!(
+            hex::encode(hx.to_le_bytes()),
+            hex::encode(xxh3.sum(&data).unwrap().to_le_bytes())
+        );
+    }
+
+    #[test]
+    fn test_read_uvarint() {
+        let buffer = [148u8, 145, 6, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(read_uvarint(buffer), 100_500);
+
+        // a non-terminating varint (all continuation bits set) is malformed
+        assert_eq!(read_uvarint([0xffu8; 16]), 0);
+        // overflow in the 10th byte is rejected rather than panicking
+        assert_eq!(
+            read_uvarint([0x80u8, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 2]),
+            0
+        );
+
+        // round-trips the size embedded by a real hash
+        let hasher = Hasher::new();
+        let hash = hasher.sum(&m(500_000)).unwrap();
+        assert_eq!(Hasher::size_from_hash(hash), 500_000);
+    }
+
+    #[test]
+    fn test_imohasher_matches_sum() {
+        use std::io::Write;
+
+        let data = m(500_000);
+        let expected = Hasher::new().sum(&data).unwrap().to_le_bytes();
+
+        // via std::io::Write
+        let mut writer = ImoHasher::new();
+        writer.write_all(&data).unwrap();
+        assert_eq!(writer.finalize(), expected);
+
+        // via digest::Digest
+        let out = <ImoHasher as Digest>::digest(&data);
+        assert_eq!(out.as_slice(), &expected[..]);
+    }
+
     fn test_data_file_path(name: &str) -> String {
         let test_data_dir = "test_data";
 
@@ -279,6 +760,71 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_find_duplicates() {
+        let root = PathBuf::from(test_data_file_path("dupes"));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a.bin"), b"hello world").unwrap();
+        fs::write(root.join("sub").join("b.bin"), b"hello world").unwrap(); // dup of a.bin
+        fs::write(root.join("c.bin"), b"xxxxxxxxxxx").unwrap(); // same size, other content
+        fs::write(root.join("d.bin"), b"short").unwrap(); // unique size
+
+        let groups = Hasher::new().find_duplicates(&root).unwrap();
+        assert_eq!(groups.len(), 1);
+
+        let paths = groups.values().next().unwrap();
+        let mut names: Vec<String> = paths
+            .iter()
+            .map(|p| p.file_name().unwrap().to_str().unwrap().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["a.bin", "b.bin"]);
+    }
+
+    #[test]
+    fn test_hash_cache() {
+        let cache_path = PathBuf::from(test_data_file_path("cache.imo"));
+        let _ = fs::remove_file(&cache_path);
+        let key = Path::new("/some/file");
+
+        {
+            let mut cache = HashCache::open(&cache_path).unwrap();
+            assert_eq!(cache.get(key, 123, 456), None);
+
+            cache.insert(key, 123, 456, 0xdead_beef);
+            assert_eq!(cache.get(key, 123, 456), Some(0xdead_beef));
+
+            // a changed mtime or length invalidates the entry
+            assert_eq!(cache.get(key, 999, 456), None);
+            assert_eq!(cache.get(key, 123, 789), None);
+
+            // re-inserting the same path replaces the entry in place
+            cache.insert(key, 321, 654, 0x1234);
+            assert_eq!(cache.get(key, 321, 654), Some(0x1234));
+            assert_eq!(cache.get(key, 123, 456), None);
+        }
+
+        // the table is flushed on drop and reopened with the prior entry intact
+        {
+            let cache = HashCache::open(&cache_path).unwrap();
+            assert_eq!(cache.get(key, 321, 654), Some(0x1234));
+        }
+
+        // a full table accepts no further distinct keys
+        let full_path = PathBuf::from(test_data_file_path("cache_full.imo"));
+        let _ = fs::remove_file(&full_path);
+        let mut cache = HashCache::with_capacity(&full_path, 4).unwrap();
+        for i in 0..4u64 {
+            cache.insert(&PathBuf::from(format!("/f/{i}")), i, i, i as u128);
+        }
+        for i in 0..4u64 {
+            assert_eq!(cache.get(&PathBuf::from(format!("/f/{i}")), i, i), Some(i as u128));
+        }
+        cache.insert(&PathBuf::from("/f/overflow"), 9, 9, 9);
+        assert_eq!(cache.get(&PathBuf::from("/f/overflow"), 9, 9), None);
+    }
+
     fn m(n: usize) -> Vec<u8> {
         let mut buffer: Vec<u8> = Vec::new();
         let mut md5 = Md5::new();